@@ -0,0 +1,183 @@
+//! Dotenv parsing for the `env_file` field.
+//!
+//! Kept free of `zellij_tile`, so the tests build and run natively with `just test`
+
+use std::path::{Component, Path};
+
+/// Parses `KEY=VALUE` lines, skipping blank lines and `#` comments.
+/// Values are taken literally, quotes only group text and nothing is expanded
+pub fn parse_env_file(contents: &str) -> Result<Vec<(String, String)>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            Some(parse_env_line(line).map_err(|error| format!("line {}: {}", index + 1, error)))
+        })
+        .collect()
+}
+
+fn parse_env_line(line: &str) -> Result<(String, String), String> {
+    let line = line
+        .strip_prefix("export ")
+        .map_or(line, |line| line.trim_start());
+
+    let Some((key, value)) = line.split_once('=') else {
+        return Err("expected KEY=VALUE".to_string());
+    };
+
+    let is_valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_valid_key {
+        return Err(format!("invalid key: {:?}", key));
+    }
+
+    let (value, rest) = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => value[1..]
+            .split_once(quote)
+            .ok_or_else(|| "unterminated quote".to_string())?,
+        _ => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+    };
+
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("unexpected text after value, quote values with spaces".to_string());
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Builds a `sh` command exporting the variables, each value single-quoted
+pub fn env_export_script(env: &[(String, String)]) -> String {
+    let assignments = env
+        .iter()
+        .map(|(key, value)| format!("{}='{}'", key, value.replace('\'', r"'\''")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("export {}; exec \"$@\"", assignments)
+}
+
+/// Rejects absolute paths and `..`, so the file stays inside the host folder
+pub fn env_file_path(env_file: &str) -> Result<&Path, String> {
+    let path = Path::new(env_file);
+    let is_inside_host_folder = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_inside_host_folder {
+        return Err(format!(
+            "env file must be a relative path inside the host folder: {}",
+            env_file
+        ));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_env_file_reads_assignments() {
+        let contents = "A=1\nexport B=two\n_C=\n";
+        assert_eq!(
+            parse_env_file(contents),
+            Ok(env(&[("A", "1"), ("B", "two"), ("_C", "")]))
+        );
+    }
+
+    #[test]
+    fn parse_env_file_skips_blank_lines_and_comments() {
+        let contents = "# comment\n\n   \nA=1\n  # indented comment\n";
+        assert_eq!(parse_env_file(contents), Ok(env(&[("A", "1")])));
+    }
+
+    #[test]
+    fn parse_env_file_strips_inline_comments() {
+        let contents = "A=v # comment\nB=\"x y\" # comment\nC=v#not-a-comment\n";
+        assert_eq!(
+            parse_env_file(contents),
+            Ok(env(&[("A", "v"), ("B", "x y"), ("C", "v#not-a-comment")]))
+        );
+    }
+
+    #[test]
+    fn parse_env_file_strips_quotes() {
+        let contents = "A=\"double quoted\"\nB='single # quoted'\nC=\"\"\n";
+        assert_eq!(
+            parse_env_file(contents),
+            Ok(env(&[
+                ("A", "double quoted"),
+                ("B", "single # quoted"),
+                ("C", "")
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_env_file_rejects_invalid_keys() {
+        assert!(parse_env_file("=value").is_err());
+        assert!(parse_env_file("A B=value").is_err());
+        assert!(parse_env_file("A =value").is_err());
+        assert!(parse_env_file("1A=value").is_err());
+        assert!(parse_env_file("no_assignment").is_err());
+    }
+
+    #[test]
+    fn parse_env_file_keeps_shell_syntax_literal() {
+        let contents = "URL=postgres://h/db?sslmode=require&pool=5\nA=$(cmd)\nB=\"`cmd` $HOME\"";
+        assert_eq!(
+            parse_env_file(contents),
+            Ok(env(&[
+                ("URL", "postgres://h/db?sslmode=require&pool=5"),
+                ("A", "$(cmd)"),
+                ("B", "`cmd` $HOME"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn env_export_script_single_quotes_values() {
+        assert_eq!(
+            env_export_script(&env(&[("URL", "a&b=1"), ("A", "$(cmd)"), ("B", "`cmd`")])),
+            "export URL='a&b=1' A='$(cmd)' B='`cmd`'; exec \"$@\""
+        );
+        assert_eq!(
+            env_export_script(&env(&[("A", "it's")])),
+            r#"export A='it'\''s'; exec "$@""#
+        );
+    }
+
+    #[test]
+    fn parse_env_file_rejects_unparseable_values() {
+        assert!(parse_env_file("A=x y").is_err());
+        assert!(parse_env_file("A=\"unterminated").is_err());
+        assert!(parse_env_file("A=\"x\"y").is_err());
+    }
+
+    #[test]
+    fn parse_env_file_reports_line_number() {
+        assert_eq!(
+            parse_env_file("A=1\n\nB C=2"),
+            Err("line 3: invalid key: \"B C\"".to_string())
+        );
+    }
+
+    #[test]
+    fn env_file_path_stays_inside_host_folder() {
+        assert!(env_file_path(".env").is_ok());
+        assert!(env_file_path("./config/dev.env").is_ok());
+        assert!(env_file_path("/etc/environment").is_err());
+        assert!(env_file_path("../.env").is_err());
+        assert!(env_file_path("config/../../.env").is_err());
+    }
+}
@@ -1,18 +1,42 @@
-use serde::{Deserialize, Serialize};
+mod env_file;
+
+use env_file::{env_export_script, env_file_path, parse_env_file};
+use serde::{
+    de::{self, DeserializeOwned, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 use serde_constant::ConstBool;
 use std::{
     collections::{BTreeMap, HashMap},
-    mem,
-    path::PathBuf,
+    fmt, fs, mem,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use zellij_tile::prelude::*;
 
 #[derive(Clone)]
 enum TogglerPaneState {
     /// Pane requested, waiting for CommandPaneOpened
-    Opening { pipe_id: String, is_toggle: bool },
+    Opening {
+        /// Pipe to reply to once opened, None for panes reopened by toggler::rerun-all
+        pipe_id: Option<String>,
+        is_toggle: bool,
+        return_tab: Option<usize>,
+        /// Focus the pane once opened, false for panes reopened by toggler::rerun-all
+        focus: bool,
+        /// Tab position to move the pane to once opened, used by toggler::rerun-all
+        move_to_tab: Option<usize>,
+        /// Id of the latest spawn, panes opened by earlier spawns are stale
+        open_id: u64,
+    },
     /// Pane is open
-    Opened { zellij_pane_id: u32 },
+    Opened {
+        zellij_pane_id: u32,
+        /// Tab to switch back to after the pane is closed
+        return_tab: Option<usize>,
+        /// Unix timestamp in seconds
+        opened_at: u64,
+    },
     /// Close requested, waiting for PaneClosed/CommandPaneExited
     Closing {
         zellij_pane_id: u32,
@@ -21,20 +45,198 @@ enum TogglerPaneState {
     },
 }
 
+/// Open reply held back until the command survives the exit check
+struct PendingOpenReply {
+    pipe_id: String,
+    is_toggle: bool,
+    deadline: Instant,
+}
+
+/// Failed open waiting to be retried
+struct PendingRetry {
+    pane_id: String,
+    deadline: Instant,
+}
+
 #[derive(Default)]
 struct TogglerState {
     panes: HashMap<String, TogglerPaneState>,
+    /// Last command used to open each pane, kept after the pane is closed
+    commands: HashMap<String, CommandConfig>,
+    /// Most recently opened or toggled pane
+    last_pane_id: Option<String>,
+    /// Closed panes, most recent last
+    closed_pane_ids: Vec<String>,
+    /// Last known zellij info of each pane, kept after the pane is closed
+    last_pane_infos: HashMap<String, PaneInfo>,
+    /// Reject payloads with unrecognized fields
+    strict: bool,
+    /// Seconds to wait for an early command exit before replying to open
+    exit_check_timeout: f64,
+    /// Open replies by zellij pane id, waiting for the exit check
+    pending_open_replies: HashMap<u32, PendingOpenReply>,
+    /// Failed opens, waiting for the retry delay
+    pending_retries: Vec<PendingRetry>,
+    /// Number of opens made for the current request of each pane
+    open_attempts: HashMap<String, u32>,
+    /// Number of times each pane was reopened by a retry or toggler::rerun-all
+    restart_counts: HashMap<String, u32>,
+    /// Position of the focused tab, updated by TabUpdate
+    active_tab: Option<usize>,
+    /// Rows and columns of the focused tab viewport, updated by TabUpdate
+    viewport: Option<(usize, usize)>,
+    /// Terminal panes by zellij pane id, updated by PaneUpdate
+    terminal_panes: HashMap<u32, PaneInfo>,
+    /// Tab position of each terminal pane, updated by PaneUpdate
+    terminal_pane_tabs: HashMap<u32, usize>,
+    /// Id of the latest spawn, not cleared by toggler::reset
+    last_open_id: u64,
 }
 
 register_plugin!(TogglerState);
 
-#[derive(Clone, Deserialize)]
+/// Payload of a pipe command
+trait PipeRequest: DeserializeOwned {
+    /// Top-level fields accepted in the payload, used for strict validation
+    fn fields() -> Vec<&'static str>;
+}
+
+#[derive(Clone, Deserialize, Serialize)]
 struct CommandConfig {
     cmd: String,
-    #[serde(default)]
+    /// Either an array or a string split with shell-like quoting
+    #[serde(default, deserialize_with = "deserialize_args")]
     args: Vec<String>,
     #[serde(default)]
     cwd: Option<String>,
+    /// Name of the tab to open the pane in
+    #[serde(default)]
+    tab: Option<String>,
+    /// Open as a floating pane sized relative to the viewport
+    #[serde(default)]
+    floating: Option<FloatingGeometry>,
+    /// Reopen the pane if the command exits during the exit check
+    #[serde(default)]
+    retry: Option<RetryPolicy>,
+    /// Dotenv file relative to the host folder, sourced on every open
+    #[serde(default)]
+    env_file: Option<String>,
+    /// Text typed into the pane once it is opened, followed by Enter (a carriage return)
+    #[serde(default)]
+    input: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RetryPolicy {
+    /// Maximum number of opens, including the first one
+    attempts: u32,
+    /// Seconds to wait before reopening
+    #[serde(default, deserialize_with = "deserialize_retry_delay")]
+    delay: f64,
+}
+
+impl RetryPolicy {
+    const MAX_DELAY: f64 = 3600.0;
+}
+
+fn deserialize_retry_delay<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let delay = f64::deserialize(deserializer)?;
+    if !(0.0..=RetryPolicy::MAX_DELAY).contains(&delay) {
+        return Err(de::Error::custom(format!(
+            "retry delay must be between 0 and {} seconds",
+            RetryPolicy::MAX_DELAY
+        )));
+    }
+    Ok(delay)
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FloatingAnchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct FloatingGeometry {
+    /// Percent of the viewport columns
+    #[serde(deserialize_with = "deserialize_floating_percent")]
+    width: usize,
+    /// Percent of the viewport rows
+    #[serde(deserialize_with = "deserialize_floating_percent")]
+    height: usize,
+    #[serde(default)]
+    anchor: FloatingAnchor,
+}
+
+impl CommandConfig {
+    fn floating_coordinates(&self) -> Option<FloatingPaneCoordinates> {
+        self.floating.as_ref().map(FloatingGeometry::coordinates)
+    }
+
+    /// Command line shown as the pane title
+    fn title(&self) -> String {
+        [&self.cmd]
+            .into_iter()
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl FloatingGeometry {
+    fn coordinates(&self) -> FloatingPaneCoordinates {
+        let (width, height) = (self.width, self.height);
+
+        let x = match self.anchor {
+            FloatingAnchor::Left | FloatingAnchor::TopLeft | FloatingAnchor::BottomLeft => 0,
+            FloatingAnchor::Right | FloatingAnchor::TopRight | FloatingAnchor::BottomRight => {
+                100 - width
+            }
+            FloatingAnchor::Center | FloatingAnchor::Top | FloatingAnchor::Bottom => {
+                (100 - width) / 2
+            }
+        };
+
+        let y = match self.anchor {
+            FloatingAnchor::Top | FloatingAnchor::TopLeft | FloatingAnchor::TopRight => 0,
+            FloatingAnchor::Bottom | FloatingAnchor::BottomLeft | FloatingAnchor::BottomRight => {
+                100 - height
+            }
+            FloatingAnchor::Center | FloatingAnchor::Left | FloatingAnchor::Right => {
+                (100 - height) / 2
+            }
+        };
+
+        FloatingPaneCoordinates::default()
+            .with_x_percent(x)
+            .with_y_percent(y)
+            .with_width_percent(width)
+            .with_height_percent(height)
+    }
+}
+
+fn deserialize_floating_percent<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    let percent = usize::deserialize(deserializer)?;
+    if !(1..=100).contains(&percent) {
+        return Err(de::Error::custom(
+            "floating width and height must be between 1 and 100 percent",
+        ));
+    }
+    Ok(percent)
 }
 
 #[derive(Deserialize)]
@@ -56,6 +258,115 @@ struct ToggleRequest {
     command: CommandConfig,
 }
 
+#[derive(Deserialize)]
+struct InfoRequest {
+    pane_id: String,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MoveDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl From<MoveDirection> for Direction {
+    fn from(direction: MoveDirection) -> Self {
+        match direction {
+            MoveDirection::Left => Direction::Left,
+            MoveDirection::Right => Direction::Right,
+            MoveDirection::Up => Direction::Up,
+            MoveDirection::Down => Direction::Down,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    pane_id: String,
+    /// Swap with the neighbouring pane, or move to the next position if omitted
+    #[serde(default)]
+    direction: Option<MoveDirection>,
+}
+
+#[derive(Deserialize)]
+struct RerunAllRequest {
+    /// Only rerun panes with matching ids, `*` matches any sequence of characters
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+fn deserialize_args<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct ArgsVisitor;
+
+    impl<'de> Visitor<'de> for ArgsVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or an array of strings")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            shlex::split(value)
+                .ok_or_else(|| E::custom(format!("unbalanced quotes in args: {}", value)))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut args = Vec::new();
+            while let Some(arg) = seq.next_element()? {
+                args.push(arg);
+            }
+            Ok(args)
+        }
+    }
+
+    deserializer.deserialize_any(ArgsVisitor)
+}
+
+impl CommandConfig {
+    const FIELDS: &[&str] = &[
+        "cmd", "args", "cwd", "tab", "floating", "retry", "env_file", "input",
+    ];
+}
+
+impl PipeRequest for OpenRequest {
+    fn fields() -> Vec<&'static str> {
+        [&["pane_id"], CommandConfig::FIELDS].concat()
+    }
+}
+
+impl PipeRequest for CloseRequest {
+    fn fields() -> Vec<&'static str> {
+        vec!["pane_id"]
+    }
+}
+
+impl PipeRequest for ToggleRequest {
+    fn fields() -> Vec<&'static str> {
+        [&["pane_id"], CommandConfig::FIELDS].concat()
+    }
+}
+
+impl PipeRequest for InfoRequest {
+    fn fields() -> Vec<&'static str> {
+        vec!["pane_id"]
+    }
+}
+
+impl PipeRequest for MoveRequest {
+    fn fields() -> Vec<&'static str> {
+        vec!["pane_id", "direction"]
+    }
+}
+
+impl PipeRequest for RerunAllRequest {
+    fn fields() -> Vec<&'static str> {
+        vec!["pattern"]
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
 enum ToggleResponseAction {
@@ -69,6 +380,50 @@ struct ToggleResponse {
     action: ToggleResponseAction,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum InfoResponseState {
+    Opening,
+    Opened,
+    Closing,
+    Closed,
+}
+
+#[derive(Serialize)]
+struct InfoResponseGeometry {
+    x: usize,
+    y: usize,
+    rows: usize,
+    columns: usize,
+    is_floating: bool,
+}
+
+#[derive(Serialize)]
+struct InfoResponse {
+    ok: ConstBool<true>,
+    pane_id: String,
+    state: InfoResponseState,
+    zellij_pane_id: Option<u32>,
+    command: Option<CommandConfig>,
+    geometry: Option<InfoResponseGeometry>,
+    opened_at: Option<u64>,
+    restart_count: u32,
+}
+
+#[derive(Serialize)]
+struct RerunAllFailure {
+    pane_id: String,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct RerunAllResponse {
+    ok: ConstBool<true>,
+    pane_ids: Vec<String>,
+    /// Panes left running because their replacement could not be spawned
+    failed: Vec<RerunAllFailure>,
+}
+
 #[derive(Serialize)]
 struct OkResponse {
     ok: ConstBool<true>,
@@ -86,6 +441,35 @@ struct ErrorResponse {
     error: String,
 }
 
+#[derive(Serialize)]
+struct ExitErrorResponse {
+    ok: ConstBool<false>,
+    error: String,
+    exit_code: i32,
+}
+
+/// Matches `value` against `pattern`, where `*` matches any sequence of characters
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = value.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[index + part.len()..];
+    }
+
+    rest.ends_with(last)
+}
+
 fn cli_pipe_json_output<T: Serialize>(pipe_id: &str, body: &T) {
     let body_str = serde_json::to_string(body).unwrap_or_default();
     cli_pipe_output(pipe_id, &body_str);
@@ -93,18 +477,33 @@ fn cli_pipe_json_output<T: Serialize>(pipe_id: &str, body: &T) {
 }
 
 impl ZellijPlugin for TogglerState {
-    fn load(&mut self, _configuration: BTreeMap<String, String>) {
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.strict = configuration
+            .get("strict")
+            .is_some_and(|value| value == "true");
+        self.exit_check_timeout = configuration
+            .get("exit_check_timeout")
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|timeout| timeout.is_finite())
+            .map_or(Self::DEFAULT_EXIT_CHECK_TIMEOUT, |timeout| {
+                timeout.clamp(0.0, Self::MAX_EXIT_CHECK_TIMEOUT)
+            });
+
         request_permission(&[
             PermissionType::RunCommands,
             PermissionType::ChangeApplicationState,
             PermissionType::ReadApplicationState,
             PermissionType::ReadCliPipes,
+            PermissionType::WriteToStdin,
         ]);
         subscribe(&[
             EventType::CommandPaneOpened,
             EventType::CommandPaneExited,
             EventType::PaneClosed,
             EventType::PermissionRequestResult,
+            EventType::TabUpdate,
+            EventType::PaneUpdate,
+            EventType::Timer,
         ]);
     }
 
@@ -116,11 +515,20 @@ impl ZellijPlugin for TogglerState {
             Event::CommandPaneOpened(pane_id, context) => {
                 self.handle_pane_opened_event(pane_id, context);
             }
-            Event::CommandPaneExited(pane_id, _exit_code, _context) => {
-                self.handle_pane_exited_event(pane_id);
+            Event::CommandPaneExited(pane_id, exit_code, _context) => {
+                self.handle_pane_exited_event(pane_id, exit_code);
             }
             Event::PaneClosed(PaneId::Terminal(pane_id)) => {
-                self.handle_pane_exited_event(pane_id);
+                self.handle_pane_exited_event(pane_id, None);
+            }
+            Event::Timer(_) => {
+                self.handle_timer_event();
+            }
+            Event::TabUpdate(tabs) => {
+                self.handle_tab_update_event(&tabs);
+            }
+            Event::PaneUpdate(pane_manifest) => {
+                self.handle_pane_update_event(pane_manifest);
             }
             _ => {}
         }
@@ -137,20 +545,50 @@ impl ZellijPlugin for TogglerState {
 
         match pipe_name {
             "toggler::open" => {
-                if let Some(req) = Self::payload_or_send_error::<OpenRequest>(&pipe_id, payload) {
+                if let Some(req) = self.payload_or_send_error::<OpenRequest>(&pipe_id, payload) {
                     self.handle_open_pipe(&pipe_id, &req);
                 }
             }
             "toggler::close" => {
-                if let Some(req) = Self::payload_or_send_error::<CloseRequest>(&pipe_id, payload) {
+                if let Some(req) = self.payload_or_send_error::<CloseRequest>(&pipe_id, payload) {
                     self.handle_close_pipe(&pipe_id, &req);
                 }
             }
             "toggler::toggle" => {
-                if let Some(req) = Self::payload_or_send_error::<ToggleRequest>(&pipe_id, payload) {
+                if let Some(req) = self.payload_or_send_error::<ToggleRequest>(&pipe_id, payload) {
                     self.handle_toggle_pipe(&pipe_id, &req);
                 }
             }
+            "toggler::last" => {
+                self.handle_last_pipe(&pipe_id);
+            }
+            "toggler::undo" => {
+                self.handle_undo_pipe(&pipe_id);
+            }
+            "toggler::info" => {
+                if let Some(req) = self.payload_or_send_error::<InfoRequest>(&pipe_id, payload) {
+                    self.handle_info_pipe(&pipe_id, &req);
+                }
+            }
+            "toggler::reset" => {
+                self.handle_reset_pipe(&pipe_id);
+            }
+            "toggler::move" => {
+                if let Some(req) = self.payload_or_send_error::<MoveRequest>(&pipe_id, payload) {
+                    self.handle_move_pipe(&pipe_id, &req);
+                }
+            }
+            "toggler::rerun-all" => {
+                let payload = if payload.trim().is_empty() {
+                    "{}"
+                } else {
+                    payload
+                };
+                if let Some(req) = self.payload_or_send_error::<RerunAllRequest>(&pipe_id, payload)
+                {
+                    self.handle_rerun_all_pipe(&pipe_id, &req);
+                }
+            }
             _ => {
                 cli_pipe_json_output(
                     &pipe_id,
@@ -172,8 +610,30 @@ impl ZellijPlugin for TogglerState {
 
 impl TogglerState {
     const PANE_ID_CONTEXT: &str = "__toggler_pane_id";
+    const OPEN_ID_CONTEXT: &str = "__toggler_open_id";
+    const DEFAULT_EXIT_CHECK_TIMEOUT: f64 = 0.5;
+    const MAX_EXIT_CHECK_TIMEOUT: f64 = 60.0;
+    const HOST_FOLDER: &str = "/host";
+
+    fn payload_or_send_error<T: PipeRequest>(&self, pipe_id: &str, payload: &str) -> Option<T> {
+        if self.strict {
+            let fields = T::fields();
+            if let Some(unknown_field) = Self::find_unknown_field(payload, &fields) {
+                cli_pipe_json_output(
+                    pipe_id,
+                    &ErrorResponse {
+                        ok: ConstBool,
+                        error: format!(
+                            "unknown field: {}, expected one of: {}",
+                            unknown_field,
+                            fields.join(", ")
+                        ),
+                    },
+                );
+                return None;
+            }
+        }
 
-    fn payload_or_send_error<'d, T: Deserialize<'d>>(pipe_id: &str, payload: &'d str) -> Option<T> {
         match serde_json::from_str::<T>(payload) {
             Err(json_error) => {
                 cli_pipe_json_output(
@@ -189,6 +649,17 @@ impl TogglerState {
         }
     }
 
+    fn find_unknown_field(payload: &str, fields: &[&str]) -> Option<String> {
+        let Ok(serde_json::Value::Object(object)) = serde_json::from_str(payload) else {
+            return None;
+        };
+
+        object
+            .keys()
+            .find(|key| !fields.contains(&key.as_str()))
+            .cloned()
+    }
+
     fn handle_open_pipe(&mut self, pipe_id: &str, payload: &OpenRequest) {
         match self.panes.get(&payload.pane_id) {
             Some(TogglerPaneState::Opened { .. }) => {
@@ -219,15 +690,31 @@ impl TogglerState {
                 );
             }
             None => {
-                self.start_opening_pane(pipe_id, &payload.pane_id, false, &payload.command);
+                self.start_opening_pane(
+                    pipe_id,
+                    &payload.pane_id,
+                    false,
+                    &payload.command,
+                    payload.command.floating_coordinates(),
+                );
             }
         }
     }
 
     fn handle_close_pipe(&mut self, pipe_id: &str, payload: &CloseRequest) {
         match self.panes.get(&payload.pane_id) {
-            Some(TogglerPaneState::Opened { zellij_pane_id }) => {
-                self.start_closing_pane(pipe_id, &payload.pane_id, *zellij_pane_id, false);
+            Some(TogglerPaneState::Opened {
+                zellij_pane_id,
+                return_tab,
+                ..
+            }) => {
+                self.start_closing_pane(
+                    pipe_id,
+                    &payload.pane_id,
+                    *zellij_pane_id,
+                    *return_tab,
+                    false,
+                );
             }
             Some(TogglerPaneState::Opening { .. }) => {
                 cli_pipe_json_output(
@@ -261,8 +748,226 @@ impl TogglerState {
 
     fn handle_toggle_pipe(&mut self, pipe_id: &str, payload: &ToggleRequest) {
         match self.panes.get(&payload.pane_id) {
-            Some(TogglerPaneState::Opened { zellij_pane_id }) => {
-                self.start_closing_pane(pipe_id, &payload.pane_id, *zellij_pane_id, true);
+            Some(TogglerPaneState::Opened {
+                zellij_pane_id,
+                return_tab,
+                ..
+            }) => {
+                self.start_closing_pane(
+                    pipe_id,
+                    &payload.pane_id,
+                    *zellij_pane_id,
+                    *return_tab,
+                    true,
+                );
+                self.last_pane_id = Some(payload.pane_id.clone());
+            }
+            Some(TogglerPaneState::Opening { .. }) | Some(TogglerPaneState::Closing { .. }) => {
+                cli_pipe_json_output(
+                    pipe_id,
+                    &WarningResponse {
+                        ok: ConstBool,
+                        warning: "pane is transitioning".to_string(),
+                    },
+                );
+            }
+            None => {
+                self.start_opening_pane(
+                    pipe_id,
+                    &payload.pane_id,
+                    true,
+                    &payload.command,
+                    payload.command.floating_coordinates(),
+                );
+            }
+        }
+    }
+
+    fn handle_last_pipe(&mut self, pipe_id: &str) {
+        let last_pane = self.last_pane_id.as_ref().and_then(|pane_id| {
+            self.commands
+                .get(pane_id)
+                .map(|command| (pane_id.clone(), command.clone()))
+        });
+
+        let Some((pane_id, command)) = last_pane else {
+            cli_pipe_json_output(
+                pipe_id,
+                &WarningResponse {
+                    ok: ConstBool,
+                    warning: "no recently used pane".to_string(),
+                },
+            );
+            return;
+        };
+
+        self.handle_toggle_pipe(pipe_id, &ToggleRequest { pane_id, command });
+    }
+
+    fn handle_undo_pipe(&mut self, pipe_id: &str) {
+        let closed_pane = self.closed_pane_ids.pop().and_then(|pane_id| {
+            self.commands
+                .get(&pane_id)
+                .map(|command| (pane_id, command.clone()))
+        });
+
+        let Some((pane_id, command)) = closed_pane else {
+            cli_pipe_json_output(
+                pipe_id,
+                &WarningResponse {
+                    ok: ConstBool,
+                    warning: "no recently closed pane".to_string(),
+                },
+            );
+            return;
+        };
+
+        // Floating panes come back at their last coordinates, tiled panes wherever zellij puts them
+        let coordinates = match self.last_pane_infos.get(&pane_id) {
+            Some(pane_info) if pane_info.is_floating => Some(
+                FloatingPaneCoordinates::default()
+                    .with_x_fixed(pane_info.pane_x)
+                    .with_y_fixed(pane_info.pane_y)
+                    .with_width_fixed(pane_info.pane_columns)
+                    .with_height_fixed(pane_info.pane_rows),
+            ),
+            Some(_) => None,
+            None => command.floating_coordinates(),
+        };
+
+        self.start_opening_pane(pipe_id, &pane_id, false, &command, coordinates);
+    }
+
+    fn handle_info_pipe(&self, pipe_id: &str, payload: &InfoRequest) {
+        let command = self.commands.get(&payload.pane_id).cloned();
+
+        let (state, zellij_pane_id, opened_at) = match self.panes.get(&payload.pane_id) {
+            Some(TogglerPaneState::Opening { .. }) => (InfoResponseState::Opening, None, None),
+            Some(TogglerPaneState::Opened {
+                zellij_pane_id,
+                opened_at,
+                ..
+            }) => (
+                InfoResponseState::Opened,
+                Some(*zellij_pane_id),
+                Some(*opened_at),
+            ),
+            Some(TogglerPaneState::Closing { zellij_pane_id, .. }) => {
+                (InfoResponseState::Closing, Some(*zellij_pane_id), None)
+            }
+            None if command.is_some() => (InfoResponseState::Closed, None, None),
+            None => {
+                cli_pipe_json_output(
+                    pipe_id,
+                    &WarningResponse {
+                        ok: ConstBool,
+                        warning: "pane not found".to_string(),
+                    },
+                );
+                return;
+            }
+        };
+
+        let geometry = zellij_pane_id
+            .and_then(|zellij_pane_id| self.terminal_panes.get(&zellij_pane_id))
+            .map(|pane| InfoResponseGeometry {
+                x: pane.pane_x,
+                y: pane.pane_y,
+                rows: pane.pane_rows,
+                columns: pane.pane_columns,
+                is_floating: pane.is_floating,
+            });
+
+        cli_pipe_json_output(
+            pipe_id,
+            &InfoResponse {
+                ok: ConstBool,
+                pane_id: payload.pane_id.clone(),
+                state,
+                zellij_pane_id,
+                command,
+                geometry,
+                opened_at,
+                restart_count: self
+                    .restart_counts
+                    .get(&payload.pane_id)
+                    .copied()
+                    .unwrap_or_default(),
+            },
+        );
+    }
+
+    fn handle_reset_pipe(&mut self, pipe_id: &str) {
+        let reset_error = ErrorResponse {
+            ok: ConstBool,
+            error: "plugin was reset".to_string(),
+        };
+
+        for (_, state) in self.panes.drain() {
+            match state {
+                TogglerPaneState::Opening {
+                    pipe_id: held_pipe_id,
+                    ..
+                } => {
+                    if let Some(held_pipe_id) = held_pipe_id {
+                        cli_pipe_json_output(&held_pipe_id, &reset_error);
+                    }
+                }
+                TogglerPaneState::Opened { zellij_pane_id, .. } => {
+                    close_terminal_pane(zellij_pane_id);
+                }
+                TogglerPaneState::Closing {
+                    zellij_pane_id,
+                    pipe_id: held_pipe_id,
+                    ..
+                } => {
+                    cli_pipe_json_output(&held_pipe_id, &reset_error);
+                    close_terminal_pane(zellij_pane_id);
+                }
+            }
+        }
+
+        for (_, reply) in self.pending_open_replies.drain() {
+            cli_pipe_json_output(&reply.pipe_id, &reset_error);
+        }
+
+        self.pending_retries.clear();
+        self.open_attempts.clear();
+        self.restart_counts.clear();
+        self.commands.clear();
+        self.last_pane_id = None;
+        self.closed_pane_ids.clear();
+        self.last_pane_infos.clear();
+
+        cli_pipe_json_output(pipe_id, &OkResponse { ok: ConstBool });
+    }
+
+    fn handle_move_pipe(&mut self, pipe_id: &str, payload: &MoveRequest) {
+        match self.panes.get(&payload.pane_id) {
+            Some(TogglerPaneState::Opened { zellij_pane_id, .. }) => {
+                let is_floating = self
+                    .terminal_panes
+                    .get(zellij_pane_id)
+                    .is_some_and(|pane| pane.is_floating);
+                if is_floating {
+                    cli_pipe_json_output(
+                        pipe_id,
+                        &ErrorResponse {
+                            ok: ConstBool,
+                            error: "pane is floating".to_string(),
+                        },
+                    );
+                    return;
+                }
+
+                let zellij_pane_id = PaneId::Terminal(*zellij_pane_id);
+                match payload.direction {
+                    Some(direction) => {
+                        move_pane_with_pane_id_in_direction(zellij_pane_id, direction.into())
+                    }
+                    None => move_pane_with_pane_id(zellij_pane_id),
+                }
+                cli_pipe_json_output(pipe_id, &OkResponse { ok: ConstBool });
             }
             Some(TogglerPaneState::Opening { .. }) | Some(TogglerPaneState::Closing { .. }) => {
                 cli_pipe_json_output(
@@ -274,48 +979,387 @@ impl TogglerState {
                 );
             }
             None => {
-                self.start_opening_pane(pipe_id, &payload.pane_id, true, &payload.command);
+                cli_pipe_json_output(
+                    pipe_id,
+                    &WarningResponse {
+                        ok: ConstBool,
+                        warning: "pane not found".to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn handle_rerun_all_pipe(&mut self, pipe_id: &str, payload: &RerunAllRequest) {
+        let rerun_panes: Vec<_> = self
+            .panes
+            .iter()
+            .filter_map(|(pane_id, state)| {
+                let TogglerPaneState::Opened {
+                    zellij_pane_id,
+                    return_tab,
+                    ..
+                } = state
+                else {
+                    return None;
+                };
+                let is_match = payload
+                    .pattern
+                    .as_ref()
+                    .is_none_or(|pattern| matches_pattern(pattern, pane_id));
+                is_match.then(|| (pane_id.clone(), *zellij_pane_id, *return_tab))
+            })
+            .collect();
+
+        let mut pane_ids = Vec::with_capacity(rerun_panes.len());
+        let mut failed = Vec::new();
+        for (pane_id, zellij_pane_id, return_tab) in rerun_panes {
+            let Some(config) = self.commands.get(&pane_id).cloned() else {
+                continue;
+            };
+
+            // Leave the pane running if its replacement cannot be spawned
+            let open_id = self.next_open_id();
+            if let Err(error) = self.spawn_command_pane(
+                &pane_id,
+                &config,
+                false,
+                config.floating_coordinates(),
+                open_id,
+            ) {
+                failed.push(RerunAllFailure { pane_id, error });
+                continue;
+            }
+
+            // The replacement opens near the plugin, so pinned panes are moved back to their tab
+            let move_to_tab = config
+                .tab
+                .as_ref()
+                .and_then(|_| self.terminal_pane_tabs.get(&zellij_pane_id).copied());
+            *self.restart_counts.entry(pane_id.clone()).or_default() += 1;
+
+            if let Some(reply) = self.pending_open_replies.remove(&zellij_pane_id) {
+                Self::send_opened_response(&reply.pipe_id, reply.is_toggle);
             }
+
+            close_terminal_pane(zellij_pane_id);
+
+            self.panes.insert(
+                pane_id.clone(),
+                TogglerPaneState::Opening {
+                    pipe_id: None,
+                    is_toggle: false,
+                    return_tab,
+                    focus: false,
+                    move_to_tab,
+                    open_id,
+                },
+            );
+            pane_ids.push(pane_id);
         }
+
+        pane_ids.sort();
+        failed.sort_by(|a, b| a.pane_id.cmp(&b.pane_id));
+        cli_pipe_json_output(
+            pipe_id,
+            &RerunAllResponse {
+                ok: ConstBool,
+                pane_ids,
+                failed,
+            },
+        );
     }
 
     fn handle_pane_opened_event(&mut self, zellij_pane_id: u32, context: BTreeMap<String, String>) {
         let Some(pane_id) = context.get(Self::PANE_ID_CONTEXT) else {
             return;
         };
+        let open_id = context
+            .get(Self::OPEN_ID_CONTEXT)
+            .and_then(|open_id| open_id.parse::<u64>().ok());
 
-        let Some(pane_state) = self.panes.get_mut(pane_id) else {
+        let Some(pane_state) = self.panes.get_mut(pane_id).filter(|state| {
+            matches!(state, TogglerPaneState::Opening { open_id: current, .. } if Some(*current) == open_id)
+        }) else {
+            // Opening was abandoned by toggler::reset or superseded by a newer spawn
+            if self.find_pane_id_by_zellij_id(zellij_pane_id).is_none() {
+                close_terminal_pane(zellij_pane_id);
+            }
             return;
         };
 
-        let TogglerPaneState::Opening { pipe_id, is_toggle } =
-            mem::replace(pane_state, TogglerPaneState::Opened { zellij_pane_id })
+        let TogglerPaneState::Opening {
+            return_tab,
+            focus,
+            move_to_tab,
+            ..
+        } = *pane_state
         else {
             return;
         };
 
-        if is_toggle {
+        let TogglerPaneState::Opening {
+            pipe_id, is_toggle, ..
+        } = mem::replace(
+            pane_state,
+            TogglerPaneState::Opened {
+                zellij_pane_id,
+                return_tab,
+                opened_at: Self::unix_timestamp(),
+            },
+        )
+        else {
+            return;
+        };
+
+        if focus && return_tab.is_some() {
+            focus_terminal_pane(zellij_pane_id, false);
+        }
+
+        if let Some(tab) = move_to_tab {
+            break_panes_to_tab_with_index(&[PaneId::Terminal(zellij_pane_id)], tab, false);
+        }
+
+        if let Some(config) = self.commands.get(pane_id) {
+            if config.env_file.is_some() {
+                rename_terminal_pane(zellij_pane_id, config.title());
+            }
+            if let Some(input) = &config.input {
+                write_chars_to_pane_id(&format!("{}\r", input), PaneId::Terminal(zellij_pane_id));
+            }
+        }
+
+        let Some(pipe_id) = pipe_id else {
+            return;
+        };
+
+        if self.exit_check_timeout > 0.0 {
+            self.pending_open_replies.insert(
+                zellij_pane_id,
+                PendingOpenReply {
+                    pipe_id,
+                    is_toggle,
+                    deadline: Instant::now() + Duration::from_secs_f64(self.exit_check_timeout),
+                },
+            );
+            set_timeout(self.exit_check_timeout);
+        } else {
+            Self::send_opened_response(&pipe_id, is_toggle);
+        }
+    }
+
+    fn handle_timer_event(&mut self) {
+        let now = Instant::now();
+        self.pending_open_replies.retain(|_, reply| {
+            if reply.deadline > now {
+                return true;
+            }
+            Self::send_opened_response(&reply.pipe_id, reply.is_toggle);
+            false
+        });
+
+        let (due_retries, waiting_retries) = mem::take(&mut self.pending_retries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|retry| retry.deadline <= now);
+        self.pending_retries = waiting_retries;
+
+        for retry in due_retries {
+            let next_open_id = self.next_open_id();
+            let Some(TogglerPaneState::Opening { open_id, .. }) =
+                self.panes.get_mut(&retry.pane_id)
+            else {
+                continue;
+            };
+            *open_id = next_open_id;
+            let Some(config) = self.commands.get(&retry.pane_id) else {
+                continue;
+            };
+            match self.spawn_command_pane(
+                &retry.pane_id,
+                config,
+                true,
+                config.floating_coordinates(),
+                next_open_id,
+            ) {
+                Ok(()) => *self.restart_counts.entry(retry.pane_id).or_default() += 1,
+                Err(error) => self.handle_spawn_error(&retry.pane_id, error),
+            }
+        }
+    }
+
+    /// Returns false if the pane has no retry policy or is out of attempts
+    fn schedule_open_retry(&mut self, pane_id: &str) -> bool {
+        let Some(retry) = self
+            .commands
+            .get(pane_id)
+            .and_then(|config| config.retry.clone())
+        else {
+            return false;
+        };
+
+        let attempts = self.open_attempts.entry(pane_id.to_string()).or_insert(1);
+        if *attempts >= retry.attempts {
+            self.open_attempts.remove(pane_id);
+            return false;
+        }
+        *attempts += 1;
+
+        self.pending_retries.push(PendingRetry {
+            pane_id: pane_id.to_string(),
+            deadline: Instant::now() + Duration::from_secs_f64(retry.delay),
+        });
+        set_timeout(retry.delay);
+        true
+    }
+
+    /// Retries the open if the policy allows it, otherwise fails the open request
+    fn handle_spawn_error(&mut self, pane_id: &str, error: String) {
+        if self.schedule_open_retry(pane_id) {
+            return;
+        }
+
+        if let Some(TogglerPaneState::Opening {
+            pipe_id: Some(pipe_id),
+            ..
+        }) = self.panes.remove(pane_id)
+        {
             cli_pipe_json_output(
                 &pipe_id,
+                &ErrorResponse {
+                    ok: ConstBool,
+                    error,
+                },
+            );
+        }
+    }
+
+    fn send_opened_response(pipe_id: &str, is_toggle: bool) {
+        if is_toggle {
+            cli_pipe_json_output(
+                pipe_id,
                 &ToggleResponse {
                     ok: ConstBool,
                     action: ToggleResponseAction::Opened,
                 },
             );
         } else {
-            cli_pipe_json_output(&pipe_id, &OkResponse { ok: ConstBool });
+            cli_pipe_json_output(pipe_id, &OkResponse { ok: ConstBool });
+        }
+    }
+
+    fn handle_pane_update_event(&mut self, pane_manifest: PaneManifest) {
+        self.terminal_pane_tabs = pane_manifest
+            .panes
+            .iter()
+            .flat_map(|(tab, panes)| {
+                panes
+                    .iter()
+                    .filter(|pane| !pane.is_plugin)
+                    .map(move |pane| (pane.id, *tab))
+            })
+            .collect();
+        self.terminal_panes = pane_manifest
+            .panes
+            .into_values()
+            .flatten()
+            .filter(|pane| !pane.is_plugin)
+            .map(|pane| (pane.id, pane))
+            .collect();
+
+        for (pane_id, state) in &self.panes {
+            let (TogglerPaneState::Opened { zellij_pane_id, .. }
+            | TogglerPaneState::Closing { zellij_pane_id, .. }) = state
+            else {
+                continue;
+            };
+            if let Some(pane_info) = self.terminal_panes.get(zellij_pane_id) {
+                self.last_pane_infos
+                    .insert(pane_id.clone(), pane_info.clone());
+            }
         }
     }
 
-    fn handle_pane_exited_event(&mut self, zellij_pane_id: u32) {
-        let Some(pane_id) = self.find_pane_id_by_zellij_id(zellij_pane_id) else {
+    fn handle_tab_update_event(&mut self, tabs: &[TabInfo]) {
+        let active_tab = tabs.iter().find(|tab| tab.active);
+        self.active_tab = active_tab.map(|tab| tab.position);
+
+        let viewport = active_tab.map(|tab| (tab.viewport_rows, tab.viewport_columns));
+        if viewport != self.viewport {
+            self.viewport = viewport;
+            self.reapply_floating_geometry();
+        }
+    }
+
+    fn reapply_floating_geometry(&self) {
+        let pane_coordinates: Vec<_> = self
+            .panes
+            .iter()
+            .filter_map(|(pane_id, state)| {
+                let TogglerPaneState::Opened { zellij_pane_id, .. } = state else {
+                    return None;
+                };
+                let geometry = self.commands.get(pane_id)?.floating.as_ref()?;
+                Some((PaneId::Terminal(*zellij_pane_id), geometry.coordinates()))
+            })
+            .collect();
+
+        if !pane_coordinates.is_empty() {
+            change_floating_panes_coordinates(pane_coordinates);
+        }
+    }
+
+    fn handle_pane_exited_event(&mut self, zellij_pane_id: u32, exit_code: Option<i32>) {
+        if let Some(reply) = self.pending_open_replies.remove(&zellij_pane_id) {
+            match exit_code {
+                Some(exit_code) if exit_code != 0 => {
+                    let pane_id = self.find_pane_id_by_zellij_id(zellij_pane_id).cloned();
+                    let state = pane_id.as_ref().and_then(|pane_id| self.panes.get(pane_id));
+
+                    // A close requested during the exit check is not retried, it is answered below
+                    if let (Some(pane_id), Some(&TogglerPaneState::Opened { return_tab, .. })) =
+                        (pane_id, state)
+                    {
+                        // The open failed, so the dead pane is not an undoable close
+                        close_terminal_pane(zellij_pane_id);
+                        self.panes.remove(&pane_id);
+
+                        if self.schedule_open_retry(&pane_id) {
+                            // The retry timer assigns the id of the next spawn
+                            let open_id = self.next_open_id();
+                            self.panes.insert(
+                                pane_id,
+                                TogglerPaneState::Opening {
+                                    pipe_id: Some(reply.pipe_id),
+                                    is_toggle: reply.is_toggle,
+                                    return_tab,
+                                    focus: true,
+                                    move_to_tab: None,
+                                    open_id,
+                                },
+                            );
+                            return;
+                        }
+
+                        Self::send_exit_error_response(&reply.pipe_id, exit_code);
+                        return;
+                    }
+
+                    Self::send_exit_error_response(&reply.pipe_id, exit_code);
+                }
+                _ => Self::send_opened_response(&reply.pipe_id, reply.is_toggle),
+            }
+        }
+
+        let Some(pane_id) = self.find_pane_id_by_zellij_id(zellij_pane_id).cloned() else {
             return;
         };
 
-        let Some(state) = self.panes.remove(&pane_id.clone()) else {
+        let Some(state) = self.panes.remove(&pane_id) else {
             return;
         };
 
+        self.closed_pane_ids.push(pane_id);
+
         let TogglerPaneState::Closing {
             pipe_id, is_toggle, ..
         } = state
@@ -336,6 +1380,29 @@ impl TogglerState {
         }
     }
 
+    fn send_exit_error_response(pipe_id: &str, exit_code: i32) {
+        cli_pipe_json_output(
+            pipe_id,
+            &ExitErrorResponse {
+                ok: ConstBool,
+                error: format!("command exited with code {}", exit_code),
+                exit_code,
+            },
+        );
+    }
+
+    fn next_open_id(&mut self) -> u64 {
+        self.last_open_id += 1;
+        self.last_open_id
+    }
+
+    fn unix_timestamp() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+
     fn find_pane_id_by_zellij_id(&self, zellij_pane_id: u32) -> Option<&String> {
         self.panes
             .iter()
@@ -357,23 +1424,98 @@ impl TogglerState {
         pane_id: &str,
         is_toggle: bool,
         config: &CommandConfig,
+        coordinates: Option<FloatingPaneCoordinates>,
     ) {
         block_cli_pipe_input(pipe_id);
 
+        let return_tab = config.tab.as_ref().and(self.active_tab);
+
+        self.commands.insert(pane_id.to_string(), config.clone());
+        self.open_attempts.remove(pane_id);
+        self.last_pane_id = Some(pane_id.to_string());
+        self.closed_pane_ids
+            .retain(|closed_pane_id| closed_pane_id != pane_id);
+
+        let open_id = self.next_open_id();
         self.panes.insert(
             pane_id.to_string(),
             TogglerPaneState::Opening {
-                pipe_id: pipe_id.to_string(),
+                pipe_id: Some(pipe_id.to_string()),
                 is_toggle,
+                return_tab,
+                focus: true,
+                move_to_tab: None,
+                open_id,
             },
         );
 
+        if let Err(error) = self.spawn_command_pane(pane_id, config, true, coordinates, open_id) {
+            self.handle_spawn_error(pane_id, error);
+        }
+    }
+
+    fn spawn_command_pane(
+        &self,
+        pane_id: &str,
+        config: &CommandConfig,
+        focus_tab: bool,
+        coordinates: Option<FloatingPaneCoordinates>,
+        open_id: u64,
+    ) -> Result<(), String> {
         let mut cmd_context = BTreeMap::new();
         cmd_context.insert(Self::PANE_ID_CONTEXT.to_string(), pane_id.to_string());
+        cmd_context.insert(Self::OPEN_ID_CONTEXT.to_string(), open_id.to_string());
+
+        let mut cmd = match &config.env_file {
+            Some(env_file) => {
+                let env_file = env_file_path(env_file)?;
+                let contents = fs::read_to_string(Path::new(Self::HOST_FOLDER).join(env_file))
+                    .map_err(|io_error| {
+                        format!("cannot read env file {}: {}", env_file.display(), io_error)
+                    })?;
+                let env = parse_env_file(&contents).map_err(|parse_error| {
+                    format!("invalid env file {}: {}", env_file.display(), parse_error)
+                })?;
 
-        let mut cmd = CommandToRun::new_with_args(&config.cmd, config.args.clone());
+                // CommandToRun has no environment, so the parsed values are exported by sh.
+                // The pane is renamed to the command once opened
+                if env.is_empty() {
+                    CommandToRun::new_with_args(&config.cmd, config.args.clone())
+                } else {
+                    let args = [
+                        "-c".to_string(),
+                        env_export_script(&env),
+                        "sh".to_string(),
+                        config.cmd.clone(),
+                    ]
+                    .into_iter()
+                    .chain(config.args.iter().cloned())
+                    .collect();
+                    CommandToRun::new_with_args("sh", args)
+                }
+            }
+            None => CommandToRun::new_with_args(&config.cmd, config.args.clone()),
+        };
         cmd.cwd = config.cwd.as_ref().map(PathBuf::from);
-        open_command_pane_near_plugin(cmd, cmd_context);
+
+        // Without focusing the tab, the pane opens near the plugin
+        let open_in_tab = focus_tab && config.tab.is_some();
+        if let Some(tab_name) = config.tab.as_ref().filter(|_| open_in_tab) {
+            focus_or_create_tab(tab_name);
+        }
+
+        match (open_in_tab, coordinates) {
+            (true, Some(coordinates)) => {
+                open_command_pane_floating(cmd, Some(coordinates), cmd_context)
+            }
+            (true, None) => open_command_pane(cmd, cmd_context),
+            (false, Some(coordinates)) => {
+                open_command_pane_floating_near_plugin(cmd, Some(coordinates), cmd_context)
+            }
+            (false, None) => open_command_pane_near_plugin(cmd, cmd_context),
+        }
+
+        Ok(())
     }
 
     fn start_closing_pane(
@@ -381,6 +1523,7 @@ impl TogglerState {
         pipe_id: &str,
         pane_id: &str,
         zellij_pane_id: u32,
+        return_tab: Option<usize>,
         is_toggle: bool,
     ) {
         block_cli_pipe_input(pipe_id);
@@ -394,5 +1537,9 @@ impl TogglerState {
             },
         );
         close_terminal_pane(zellij_pane_id);
+
+        if let Some(return_tab) = return_tab {
+            go_to_tab(return_tab as u32);
+        }
     }
 }